@@ -1258,6 +1258,24 @@ mod tests {
         assert_matches!(conn.last_transaction(),
                         "[[?tx :db/txInstant ?ms ?tx true]
                           [?tx :test/ref ?tx ?tx true]]");
+
+        // Test that we can use (transaction-instant) as a value, and that it agrees with the
+        // transaction's :db/txInstant.
+        assert_transact!(conn, "[[:db/add 555 :db/ident :test/instant]
+                                 [:db/add 555 :db/valueType :db.type/instant]]");
+        assert_transact!(conn, "[[:db/add 333 :test/instant (transaction-instant)]]");
+        assert_matches!(conn.last_transaction(),
+                        "[[333 :test/instant ?ms ?tx true]
+                          [?tx :db/txInstant ?ms ?tx true]]");
+
+        // (transaction-instant) can be used to assert :db/txInstant itself.
+        assert_transact!(conn, "[[:db/add (transaction-tx) :db/txInstant (transaction-instant)]
+                                 [:db/add 333 :test/instant (transaction-instant)]]");
+
+        // But it can't disagree with an explicit :db/txInstant.
+        assert_transact!(conn, "[[:db/add (transaction-tx) :db/txInstant #inst \"2017-06-16T00:59:11.257Z\"]
+                                 [:db/add 333 :test/instant (transaction-instant)]]",
+                         Err("not yet implemented: Transaction function transaction-instant disagrees with the asserted :db/txInstant"));
     }
 
     #[test]
@@ -157,6 +157,9 @@ pub struct Tx<'conn, 'a, W> where W: TransactWatcher {
 
     /// The transaction ID of the transaction.
     tx_id: Entid,
+
+    /// The instant produced by `(transaction-instant)`, if the transaction has used it.
+    tx_instant: Option<DateTime<Utc>>,
 }
 
 /// Remove any :db/id value from the given map notation, converting the returned value into
@@ -199,6 +202,7 @@ impl<'conn, 'a, W> Tx<'conn, 'a, W> where W: TransactWatcher {
             schema: schema,
             watcher: watcher,
             tx_id: tx_id,
+            tx_instant: None,
         }
     }
 
@@ -250,7 +254,7 @@ impl<'conn, 'a, W> Tx<'conn, 'a, W> where W: TransactWatcher {
     ///
     /// The `Term` instances produce share interned TempId and LookupRef handles, and we return the
     /// interned handle sets so that consumers can ensure all handles are used appropriately.
-    fn entities_into_terms_with_temp_ids_and_lookup_refs<I, V: TransactableValue>(&self, entities: I) -> Result<(Vec<TermWithTempIdsAndLookupRefs>, InternSet<TempId>, InternSet<AVPair>)> where I: IntoIterator<Item=Entity<V>> {
+    fn entities_into_terms_with_temp_ids_and_lookup_refs<I, V: TransactableValue>(&mut self, entities: I) -> Result<(Vec<TermWithTempIdsAndLookupRefs>, InternSet<TempId>, InternSet<AVPair>)> where I: IntoIterator<Item=Entity<V>> {
         struct InProcess<'a> {
             partition_map: &'a PartitionMap,
             schema: &'a Schema,
@@ -475,6 +479,9 @@ impl<'conn, 'a, W> Tx<'conn, 'a, W> where W: TransactWatcher {
                             entmod::ValuePlace::TxFunction(ref tx_function) => {
                                 let typed_value = match tx_function.op.0.as_str() {
                                     "transaction-tx" => TypedValue::Ref(self.tx_id),
+                                    // The instant is fixed on first use; any explicit :db/txInstant
+                                    // must agree with it.  See `get_or_insert_tx_instant`.
+                                    "transaction-instant" => TypedValue::Instant(*self.tx_instant.get_or_insert_with(now)),
                                     unknown @ _ => bail!(DbErrorKind::NotYetImplemented(format!("Unknown transaction function {}", unknown))),
                                 };
 
@@ -744,7 +751,7 @@ impl<'conn, 'a, W> Tx<'conn, 'a, W> where W: TransactWatcher {
         // Pipeline stage 4: final terms (after rewriting) -> DB insertions.
         // Collect into non_fts_*.
 
-        tx_instant = get_or_insert_tx_instant(&mut aev_trie, &self.schema, self.tx_id)?;
+        tx_instant = get_or_insert_tx_instant(&mut aev_trie, &self.schema, self.tx_id, self.tx_instant)?;
 
         for ((a, attribute), evs) in aev_trie {
             if entids::might_update_metadata(a) {
@@ -910,7 +917,11 @@ pub(crate) fn into_aev_trie<'schema>(schema: &'schema Schema, final_populations:
 
 /// Transact [:db/add :db/txInstant tx_instant (transaction-tx)] if the trie doesn't contain it
 /// already.  Return the instant from the input or the instant inserted.
-fn get_or_insert_tx_instant<'schema>(aev_trie: &mut AEVTrie<'schema>, schema: &'schema Schema, tx_id: Entid) -> Result<DateTime<Utc>> {
+///
+/// `function_instant` is the instant produced by `(transaction-instant)`, if the transaction used
+/// it.  It is inserted in preference to the current time, and it must agree with any instant given
+/// in the input.
+fn get_or_insert_tx_instant<'schema>(aev_trie: &mut AEVTrie<'schema>, schema: &'schema Schema, tx_id: Entid, function_instant: Option<DateTime<Utc>>) -> Result<DateTime<Utc>> {
     let ars = aev_trie
         .entry((entids::DB_TX_INSTANT, schema.require_attribute_for_entid(entids::DB_TX_INSTANT)?))
         .or_insert(BTreeMap::default())
@@ -925,10 +936,17 @@ fn get_or_insert_tx_instant<'schema>(aev_trie: &mut AEVTrie<'schema>, schema: &'
 
     let first = ars.add.iter().next().cloned();
     match first {
-        Some(TypedValue::Instant(instant)) => Ok(instant),
+        Some(TypedValue::Instant(instant)) => {
+            match function_instant {
+                Some(function_instant) if function_instant != instant => {
+                    bail!(DbErrorKind::NotYetImplemented(format!("Transaction function transaction-instant disagrees with the asserted :db/txInstant")))
+                },
+                _ => Ok(instant),
+            }
+        },
         Some(_) => unreachable!(), // This is a coding error -- we should have typechecked this already.
         None => {
-            let instant = now();
+            let instant = function_instant.unwrap_or_else(now);
             ars.add.insert(instant.into());
             Ok(instant)
         },
@@ -100,10 +100,10 @@ pub struct LookupRef<V> {
 /// A "transaction function" that exposes some value determined by the current transaction.  The
 /// prototypical example is the current transaction ID, `(transaction-tx)`.
 ///
-/// A natural next step might be to expose the current transaction instant `(transaction-instant)`,
-/// but that's more difficult: the transaction itself can set the transaction instant (with some
-/// restrictions), so the transaction function must be late-binding.  Right now, that's difficult to
-/// arrange in the transactor.
+/// The current transaction instant is exposed as `(transaction-instant)`, but only in the value
+/// position.  The transaction itself can set the transaction instant (with some restrictions), and
+/// a truly late-binding function is difficult to arrange in the transactor, so the instant is fixed
+/// when the function is first used and any explicit `:db/txInstant` must agree with it.
 ///
 /// In the future, we might accept arguments; for example, perhaps we might expose `(ancestor
 /// (transaction-tx) n)` to find the n-th ancestor of the current transaction.  If we do accept
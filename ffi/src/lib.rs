@@ -1328,7 +1328,7 @@ pub unsafe extern "C" fn store_cache_attribute_bi_directional(store: *mut Store,
 pub unsafe extern "C" fn store_query<'a>(store: *mut Store, query: *const c_char) -> *mut QueryBuilder<'a> {
     assert_not_null!(store);
     let query = c_char_to_string(query);
-    let store = &mut *store;
+    let store = &*store;
     Box::into_raw(Box::new(QueryBuilder::new(store, query)))
 }
 
@@ -52,6 +52,10 @@
 //
 // We probably need both, but this file provides the latter.
 
+use std::collections::BTreeSet;
+use std::collections::btree_map::Entry;
+use std::mem;
+
 use edn::{
     InternSet,
     PlainSymbol,
@@ -59,9 +63,11 @@ use edn::{
 };
 use edn::entities::{
     AttributePlace,
+    EntidOrIdent,
     Entity,
     EntityPlace,
     LookupRef,
+    MapNotation,
     OpType,
     TempId,
     TxFunction,
@@ -69,15 +75,27 @@ use edn::entities::{
 };
 
 use mentat_core::{
+    Binding,
+    HasSchema,
+    Keyword,
+    KnownEntid,
     TxReport,
     TypedValue,
+    ValueType,
 };
 
 use conn::{
     InProgress,
+    Queryable,
+};
+
+use query::{
+    IntoResult,
+    QueryInputs,
 };
 
 use errors::{
+    MentatError,
     Result,
 };
 
@@ -88,6 +106,14 @@ pub struct TermBuilder {
     terms: Vec<Entity<TypedValue>>,
 }
 
+/// Accumulates nested map notation, like `{:foo/name "x" :foo/component {:foo/name "y"}}`.
+///
+/// The result can be used as the value of a `:db.type/ref` attribute, in which case the transactor
+/// explodes it into its own entity, or added as a top-level entity with `TermBuilder::add_map`.
+pub struct MapNotationBuilder {
+    map: MapNotation<TypedValue>,
+}
+
 pub struct EntityBuilder<T: BuildTerms + Sized> {
     builder: T,
     entity: EntityPlace<TypedValue>,
@@ -171,6 +197,76 @@ impl TermBuilder {
     pub fn tx_function(op: &str) -> TxFunction {
         TxFunction { op: PlainSymbol::plain(op) }
     }
+
+    /// Collect `values` into a single vector value, as in `[:db/add e :foo/many ["a" "b"]]`.
+    /// This is only meaningful for `:db.cardinality/many` attributes.
+    pub fn vector<I, V>(values: I) -> ValuePlace<TypedValue>
+    where I: IntoIterator<Item=V>,
+          V: Into<ValuePlace<TypedValue>> {
+        ValuePlace::Vector(values.into_iter().map(|v| v.into()).collect())
+    }
+
+    /// Add a top-level entity in map notation.
+    pub fn add_map(&mut self, map: MapNotationBuilder) -> Result<()> {
+        self.terms.push(Entity::MapNotation(map.build()));
+        Ok(())
+    }
+}
+
+impl MapNotationBuilder {
+    pub fn new() -> MapNotationBuilder {
+        MapNotationBuilder {
+            map: MapNotation::default(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.map.is_empty()
+    }
+
+    /// Name the entity described by this map.  Without a `:db/id`, the transactor allocates an
+    /// internal tempid, and the entity must be reachable via a component or unique identity attribute.
+    pub fn db_id<E>(&mut self, e: E) -> Result<()>
+    where E: Into<ValuePlace<TypedValue>> {
+        self.map.insert(EntidOrIdent::Ident(kw!(:db/id)), e.into());
+        Ok(())
+    }
+
+    /// Add `v` for attribute `a`.  Adding more than one value for the same attribute collects the
+    /// values into a vector, which the transactor accepts only for `:db.cardinality/many` attributes.
+    pub fn add<A, V>(&mut self, a: A, v: V) -> Result<()>
+    where A: Into<AttributePlace>,
+          V: Into<ValuePlace<TypedValue>> {
+        let AttributePlace::Entid(a) = a.into();
+        let v = v.into();
+        match self.map.entry(a) {
+            Entry::Vacant(entry) => {
+                entry.insert(v);
+            },
+            Entry::Occupied(mut entry) => {
+                let existing = entry.get_mut();
+                let previous = mem::replace(existing, ValuePlace::Vector(vec![]));
+                *existing = match previous {
+                    ValuePlace::Vector(mut vs) => {
+                        vs.push(v);
+                        ValuePlace::Vector(vs)
+                    },
+                    previous => ValuePlace::Vector(vec![previous, v]),
+                };
+            },
+        }
+        Ok(())
+    }
+
+    pub fn build(self) -> MapNotation<TypedValue> {
+        self.map
+    }
+}
+
+impl From<MapNotationBuilder> for ValuePlace<TypedValue> {
+    fn from(builder: MapNotationBuilder) -> ValuePlace<TypedValue> {
+        ValuePlace::MapNotation(builder.build())
+    }
 }
 
 impl<T> EntityBuilder<T> where T: BuildTerms {
@@ -227,6 +323,105 @@ impl<'a, 'c> InProgressBuilder<'a, 'c> {
                         Ok(report)
                    })
     }
+
+    /// Add a top-level entity in map notation.
+    pub fn add_map(&mut self, map: MapNotationBuilder) -> Result<()> {
+        self.builder.add_map(map)
+    }
+
+    /// Like `add`, but the attribute is named by keyword and checked against the current schema:
+    /// an unknown attribute or a value of the wrong type is rejected now, rather than at transact time.
+    pub fn add_kw<E, V>(&mut self, e: E, a: &Keyword, v: V) -> Result<()>
+    where E: Into<EntityPlace<TypedValue>>,
+          V: Into<ValuePlace<TypedValue>> {
+        let (attribute, v) = self.extract_kw_value(a, v.into())?;
+        self.builder.add(e, attribute, v)
+    }
+
+    /// Like `retract`, but checked against the current schema in the same way as `add_kw`.
+    pub fn retract_kw<E, V>(&mut self, e: E, a: &Keyword, v: V) -> Result<()>
+    where E: Into<EntityPlace<TypedValue>>,
+          V: Into<ValuePlace<TypedValue>> {
+        let (attribute, v) = self.extract_kw_value(a, v.into())?;
+        self.builder.retract(e, attribute, v)
+    }
+
+    fn extract_kw_value(&self, a: &Keyword, v: ValuePlace<TypedValue>) -> Result<(KnownEntid, ValuePlace<TypedValue>)> {
+        let (attribute, entid) = self.in_progress
+                                     .attribute_for_ident(a)
+                                     .ok_or_else(|| MentatError::UnknownAttribute(a.to_string()))?;
+        if let ValuePlace::Atom(ref tv) = v {
+            let provided = tv.value_type();
+            let expected = attribute.value_type;
+            // A string value for a ref attribute names a tempid.
+            let is_tempid = expected == ValueType::Ref && provided == ValueType::String;
+            if provided != expected && !is_tempid {
+                bail!(MentatError::ValueTypeMismatch(provided, expected));
+            }
+        }
+        Ok((entid, v))
+    }
+
+    /// Retract every current value of `a` for `e`.
+    pub fn retract_attribute(&mut self, e: KnownEntid, a: &Keyword) -> Result<()> {
+        for v in self.in_progress.lookup_values_for_attribute(e.0, a)? {
+            self.builder.retract(e, a.clone(), v)?;
+        }
+        Ok(())
+    }
+
+    /// Retract every datom about `e`, and every datom that refers to `e`.  Entities reachable
+    /// from `e` via a `:db/isComponent` attribute are retracted in the same way, recursively.
+    ///
+    /// This is the builder equivalent of Datomic's `:db/retractEntity`.  The retractions are
+    /// computed against the current state of the `InProgress`, not against terms already added to
+    /// this builder.
+    pub fn retract_entity(&mut self, e: KnownEntid) -> Result<()> {
+        let mut seen: BTreeSet<KnownEntid> = BTreeSet::new();
+        let mut pending: Vec<KnownEntid> = vec![e];
+
+        while let Some(e) = pending.pop() {
+            if !seen.insert(e) {
+                continue;
+            }
+
+            let forward = self.in_progress.q_once("[:find ?a ?v :in ?e :where [?e ?a ?v]]",
+                                                  QueryInputs::with_value_sequence(vec![(var!(?e), e.into())]))
+                                          .into_rel_result()?;
+            for row in forward.into_iter() {
+                let mut row = row.into_iter();
+                match (row.next(), row.next()) {
+                    (Some(Binding::Scalar(TypedValue::Ref(a))), Some(Binding::Scalar(v))) => {
+                        if let TypedValue::Ref(child) = v {
+                            if self.in_progress.attribute_for_entid(a).map_or(false, |attribute| attribute.component) {
+                                pending.push(KnownEntid(child));
+                            }
+                        }
+                        self.builder.retract(e, KnownEntid(a), v)?;
+                    },
+                    _ => unreachable!("bad query result types in retract_entity"),
+                }
+            }
+
+            let reverse = self.in_progress.q_once("[:find ?x ?a :in ?e :where [?x ?a ?e]]",
+                                                  QueryInputs::with_value_sequence(vec![(var!(?e), e.into())]))
+                                          .into_rel_result()?;
+            for row in reverse.into_iter() {
+                let mut row = row.into_iter();
+                match (row.next(), row.next()) {
+                    (Some(Binding::Scalar(TypedValue::Ref(x))), Some(Binding::Scalar(TypedValue::Ref(a)))) => {
+                        // A self-reference was already retracted by the forward pass above.
+                        if x != e.0 {
+                            self.builder.retract(KnownEntid(x), KnownEntid(a), e)?;
+                        }
+                    },
+                    _ => unreachable!("bad query result types in retract_entity"),
+                }
+            }
+        }
+
+        Ok(())
+    }
 }
 
 impl<'a, 'c> BuildTerms for InProgressBuilder<'a, 'c> {
@@ -361,6 +556,181 @@ mod testing {
         builder.commit().expect("commit succeeded");
     }
 
+    #[test]
+    fn test_map_notation_builder() {
+        let mut sqlite = mentat_db::db::new_connection("").unwrap();
+        let mut conn = Conn::connect(&mut sqlite).unwrap();
+
+        conn.transact(&mut sqlite, r#"[
+            [:db/add "n" :db/ident :foo/name]
+            [:db/add "n" :db/valueType :db.type/string]
+            [:db/add "n" :db/cardinality :db.cardinality/one]
+            [:db/add "m" :db/ident :foo/many]
+            [:db/add "m" :db/valueType :db.type/string]
+            [:db/add "m" :db/cardinality :db.cardinality/many]
+            [:db/add "c" :db/ident :foo/component]
+            [:db/add "c" :db/valueType :db.type/ref]
+            [:db/add "c" :db/cardinality :db.cardinality/one]
+            [:db/add "c" :db/isComponent true]
+        ]"#).unwrap();
+
+        let mut inner = MapNotationBuilder::new();
+        inner.add(kw!(:foo/name), TypedValue::typed_string("inner")).expect("add succeeded");
+
+        let mut builder = TermBuilder::new();
+        let e_x = builder.named_tempid("x");
+        let mut outer = MapNotationBuilder::new();
+        outer.db_id(e_x).expect("db_id succeeded");
+        outer.add(kw!(:foo/name), TypedValue::typed_string("outer")).expect("add succeeded");
+        outer.add(kw!(:foo/many), TypedValue::typed_string("a")).expect("add succeeded");
+        outer.add(kw!(:foo/many), TypedValue::typed_string("b")).expect("add succeeded");
+        outer.add(kw!(:foo/component), inner).expect("add succeeded");
+        builder.add_map(outer).expect("add_map succeeded");
+
+        let e_y = builder.named_tempid("y");
+        builder.add(e_y, kw!(:foo/many), TermBuilder::vector(vec![TypedValue::typed_string("c"),
+                                                                  TypedValue::typed_string("d")]))
+               .expect("add succeeded");
+
+        let report = {
+            let mut in_progress = conn.begin_transaction(&mut sqlite).expect("begun successfully");
+            let report = in_progress.transact_builder(builder).expect("transact succeeded");
+            in_progress.commit().expect("commit succeeded");
+            report
+        };
+
+        let x = *report.tempids.get("x").expect("our tempid has an ID");
+        let y = *report.tempids.get("y").expect("our tempid has an ID");
+        assert_eq!(conn.lookup_value_for_attribute(&mut sqlite, x, &kw!(:foo/name)).expect("lookup succeeded"),
+                   Some(TypedValue::typed_string("outer")));
+        assert_eq!(conn.lookup_values_for_attribute(&mut sqlite, x, &kw!(:foo/many)).expect("lookup succeeded").len(), 2);
+        assert_eq!(conn.lookup_values_for_attribute(&mut sqlite, y, &kw!(:foo/many)).expect("lookup succeeded").len(), 2);
+
+        let inner = match conn.lookup_value_for_attribute(&mut sqlite, x, &kw!(:foo/component)).expect("lookup succeeded") {
+            Some(TypedValue::Ref(inner)) => inner,
+            v => panic!("expected a component ref, got {:?}", v),
+        };
+        assert_eq!(conn.lookup_value_for_attribute(&mut sqlite, inner, &kw!(:foo/name)).expect("lookup succeeded"),
+                   Some(TypedValue::typed_string("inner")));
+    }
+
+    #[test]
+    fn test_in_progress_builder_kw() {
+        let mut sqlite = mentat_db::db::new_connection("").unwrap();
+        let mut conn = Conn::connect(&mut sqlite).unwrap();
+
+        conn.transact(&mut sqlite, r#"[
+            [:db/add "o" :db/ident :foo/one]
+            [:db/add "o" :db/valueType :db.type/long]
+            [:db/add "o" :db/cardinality :db.cardinality/one]
+            [:db/add "r" :db/ident :foo/ref]
+            [:db/add "r" :db/valueType :db.type/ref]
+            [:db/add "r" :db/cardinality :db.cardinality/one]
+        ]"#).unwrap();
+
+        let in_progress = conn.begin_transaction(&mut sqlite).expect("begun successfully");
+        let mut builder = in_progress.builder();
+        let e_x = builder.named_tempid("x");
+
+        match builder.add_kw(e_x.clone(), &kw!(:foo/one), TypedValue::typed_string("nope")).expect_err("expected add_kw to fail") {
+            MentatError::ValueTypeMismatch(provided, expected) => {
+                assert_eq!(provided, ValueType::String);
+                assert_eq!(expected, ValueType::Long);
+            },
+            e => panic!("expected ValueTypeMismatch error, got {:?}", e),
+        }
+
+        match builder.add_kw(e_x.clone(), &kw!(:foo/bat), TypedValue::Long(1)).expect_err("expected add_kw to fail") {
+            MentatError::UnknownAttribute(msg) => assert_eq!(msg, ":foo/bat"),
+            e => panic!("expected UnknownAttribute error, got {:?}", e),
+        }
+
+        builder.add_kw(e_x.clone(), &kw!(:foo/one), TypedValue::Long(123)).expect("add succeeded");
+        builder.add_kw(e_x.clone(), &kw!(:foo/ref), TypedValue::typed_string("y")).expect("tempid is a valid ref");
+        let report = builder.commit().expect("commit succeeded");
+
+        let x = *report.tempids.get("x").expect("our tempid has an ID");
+        assert_eq!(conn.lookup_value_for_attribute(&mut sqlite, x, &kw!(:foo/one)).expect("lookup succeeded"),
+                   Some(TypedValue::Long(123)));
+    }
+
+    #[test]
+    fn test_retract_attribute() {
+        let mut sqlite = mentat_db::db::new_connection("").unwrap();
+        let mut conn = Conn::connect(&mut sqlite).unwrap();
+
+        conn.transact(&mut sqlite, r#"[
+            [:db/add "o" :db/ident :foo/one]
+            [:db/add "o" :db/valueType :db.type/long]
+            [:db/add "o" :db/cardinality :db.cardinality/one]
+            [:db/add "m" :db/ident :foo/many]
+            [:db/add "m" :db/valueType :db.type/string]
+            [:db/add "m" :db/cardinality :db.cardinality/many]
+        ]"#).unwrap();
+
+        let report = conn.transact(&mut sqlite, r#"[
+            [:db/add "x" :foo/one 123]
+            [:db/add "x" :foo/many "a"]
+            [:db/add "x" :foo/many "b"]
+        ]"#).unwrap();
+        let x = *report.tempids.get("x").expect("x");
+
+        let in_progress = conn.begin_transaction(&mut sqlite).expect("begun successfully");
+        let mut builder = in_progress.builder();
+        builder.retract_attribute(KnownEntid(x), &kw!(:foo/many)).expect("retracted");
+        builder.commit().expect("commit succeeded");
+
+        assert_eq!(conn.lookup_values_for_attribute(&mut sqlite, x, &kw!(:foo/many)).expect("lookup succeeded"),
+                   vec![]);
+        assert_eq!(conn.lookup_value_for_attribute(&mut sqlite, x, &kw!(:foo/one)).expect("lookup succeeded"),
+                   Some(TypedValue::Long(123)));
+    }
+
+    #[test]
+    fn test_retract_entity() {
+        let mut sqlite = mentat_db::db::new_connection("").unwrap();
+        let mut conn = Conn::connect(&mut sqlite).unwrap();
+
+        conn.transact(&mut sqlite, r#"[
+            [:db/add "n" :db/ident :foo/name]
+            [:db/add "n" :db/valueType :db.type/string]
+            [:db/add "n" :db/cardinality :db.cardinality/one]
+            [:db/add "c" :db/ident :foo/component]
+            [:db/add "c" :db/valueType :db.type/ref]
+            [:db/add "c" :db/cardinality :db.cardinality/one]
+            [:db/add "c" :db/isComponent true]
+            [:db/add "r" :db/ident :foo/ref]
+            [:db/add "r" :db/valueType :db.type/ref]
+            [:db/add "r" :db/cardinality :db.cardinality/one]
+        ]"#).unwrap();
+
+        let report = conn.transact(&mut sqlite, r#"[
+            [:db/add "parent" :foo/name "parent"]
+            [:db/add "parent" :foo/component "child"]
+            [:db/add "child" :foo/name "child"]
+            [:db/add "other" :foo/name "other"]
+            [:db/add "other" :foo/ref "parent"]
+        ]"#).unwrap();
+        let parent = *report.tempids.get("parent").expect("parent");
+        let child = *report.tempids.get("child").expect("child");
+        let other = *report.tempids.get("other").expect("other");
+
+        let in_progress = conn.begin_transaction(&mut sqlite).expect("begun successfully");
+        let mut builder = in_progress.builder();
+        builder.retract_entity(KnownEntid(parent)).expect("retracted");
+        builder.commit().expect("commit succeeded");
+
+        // The entity and its component are gone.
+        assert_eq!(conn.lookup_value_for_attribute(&mut sqlite, parent, &kw!(:foo/name)).expect("lookup succeeded"), None);
+        assert_eq!(conn.lookup_value_for_attribute(&mut sqlite, parent, &kw!(:foo/component)).expect("lookup succeeded"), None);
+        assert_eq!(conn.lookup_value_for_attribute(&mut sqlite, child, &kw!(:foo/name)).expect("lookup succeeded"), None);
+
+        // References to the entity are gone, but the referring entity survives.
+        assert_eq!(conn.lookup_value_for_attribute(&mut sqlite, other, &kw!(:foo/ref)).expect("lookup succeeded"), None);
+        assert_eq!(conn.lookup_value_for_attribute(&mut sqlite, other, &kw!(:foo/name)).expect("lookup succeeded"),
+                   Some(TypedValue::typed_string("other")));
+    }
+
     #[test]
     fn test_entity_builder() {
         let mut sqlite = mentat_db::db::new_connection("").unwrap();
@@ -42,11 +42,11 @@ pub struct QueryBuilder<'a> {
     query: String,
     values: BTreeMap<Variable, TypedValue>,
     types: BTreeMap<Variable, ValueType>,
-    store: &'a mut Store,
+    store: &'a Store,
 }
 
 impl<'a> QueryBuilder<'a> {
-    pub fn new<T>(store: &'a mut Store, query: T) -> QueryBuilder where T: Into<String> {
+    pub fn new<T>(store: &'a Store, query: T) -> QueryBuilder where T: Into<String> {
         QueryBuilder { query: query.into(), values: BTreeMap::new(), types: BTreeMap::new(), store }
     }
 
@@ -91,8 +91,7 @@ impl<'a> QueryBuilder<'a> {
         let values = ::std::mem::replace(&mut self.values, Default::default());
         let types = ::std::mem::replace(&mut self.types, Default::default());
         let query_inputs = QueryInputs::new(types, values)?;
-        let read = self.store.begin_read()?;
-        read.q_once(&self.query, query_inputs)
+        self.store.q_once(&self.query, query_inputs)
     }
 
     pub fn execute_scalar(&mut self) -> Result<Option<Binding>> {
@@ -140,9 +139,9 @@ mod test {
 
         let yes = report.tempids.get("u").expect("found it").clone();
 
-        let entid = QueryBuilder::new(&mut store, r#"[:find ?x .
-                                                      :in ?v
-                                                      :where [?x :foo/boolean ?v]]"#)
+        let entid = QueryBuilder::new(&store, r#"[:find ?x .
+                                                  :in ?v
+                                                  :where [?x :foo/boolean ?v]]"#)
                               .bind_value("?v", true)
                               .execute_scalar().expect("ScalarResult")
                               .map_or(None, |t| t.into_entid());
@@ -178,9 +177,9 @@ mod test {
         let l_yes = report.tempids.get("l").expect("found it").clone();
         let n_yes = report.tempids.get("n").expect("found it").clone();
 
-        let entids: Vec<i64> = QueryBuilder::new(&mut store, r#"[:find [?x ...]
-                                                                 :in ?v
-                                                                 :where [?x :foo/boolean ?v]]"#)
+        let entids: Vec<i64> = QueryBuilder::new(&store, r#"[:find [?x ...]
+                                                             :in ?v
+                                                             :where [?x :foo/boolean ?v]]"#)
                               .bind_value("?v", true)
                               .execute_coll().expect("CollResult")
                               .into_iter()
@@ -217,9 +216,9 @@ mod test {
 
         let n_yes = report.tempids.get("n").expect("found it").clone();
 
-        let results = QueryBuilder::new(&mut store, r#"[:find [?x ...]
-                                                        :in ?v
-                                                        :where [?x :foo/boolean ?v]]"#)
+        let results = QueryBuilder::new(&store, r#"[:find [?x ...]
+                                                    :in ?v
+                                                    :where [?x :foo/boolean ?v]]"#)
                               .bind_value("?v", true)
                               .execute_coll().expect("CollResult");
         let entid = results.get(1).map_or(None, |t| t.to_owned().into_entid()).expect("entid");
@@ -254,10 +253,10 @@ mod test {
 
         let n_yes = report.tempids.get("n").expect("found it").clone();
 
-        let results = QueryBuilder::new(&mut store, r#"[:find [?x, ?i]
-                                                        :in ?v ?i
-                                                        :where [?x :foo/boolean ?v]
-                                                               [?x :foo/long ?i]]"#)
+        let results = QueryBuilder::new(&store, r#"[:find [?x, ?i]
+                                                    :in ?v ?i
+                                                    :where [?x :foo/boolean ?v]
+                                                           [?x :foo/long ?i]]"#)
                               .bind_value("?v", true)
                               .bind_long("?i", 27)
                               .execute_tuple().expect("TupleResult").expect("Vec<TypedValue>");
@@ -295,10 +294,10 @@ mod test {
 
         let n_yes = report.tempids.get("n").expect("found it").clone();
 
-        let results: Vec<_> = QueryBuilder::new(&mut store, r#"[:find [?x, ?i]
-                                                                :in ?v ?i
-                                                                :where [?x :foo/boolean ?v]
-                                                                       [?x :foo/long ?i]]"#)
+        let results: Vec<_> = QueryBuilder::new(&store, r#"[:find [?x, ?i]
+                                                            :in ?v ?i
+                                                            :where [?x :foo/boolean ?v]
+                                                                   [?x :foo/long ?i]]"#)
                               .bind_value("?v", true)
                               .bind_long("?i", 27)
                               .execute_tuple().expect("TupleResult").unwrap_or(vec![]);
@@ -340,9 +339,9 @@ mod test {
             long_val: i64,
         };
 
-        let mut results: Vec<Res> = QueryBuilder::new(&mut store, r#"[:find ?x ?v ?i
-                                                                      :where [?x :foo/boolean ?v]
-                                                                             [?x :foo/long ?i]]"#)
+        let mut results: Vec<Res> = QueryBuilder::new(&store, r#"[:find ?x ?v ?i
+                                                                  :where [?x :foo/boolean ?v]
+                                                                         [?x :foo/long ?i]]"#)
                               .execute_rel().expect("RelResult")
                               .into_iter()
                               .map(|row| {
@@ -386,14 +385,38 @@ mod test {
 
         let l_yes = report.tempids.get("l").expect("found it").clone();
 
-        let results = QueryBuilder::new(&mut store, r#"[:find [?v ?i]
-                                                        :in ?x
-                                                        :where [?x :foo/boolean ?v]
-                                                               [?x :foo/long ?i]]"#)
+        let results = QueryBuilder::new(&store, r#"[:find [?v ?i]
+                                                    :in ?x
+                                                    :where [?x :foo/boolean ?v]
+                                                           [?x :foo/long ?i]]"#)
                               .bind_ref("?x", l_yes)
                               .execute_tuple().expect("TupleResult")
                               .unwrap_or(vec![]);
         assert_eq!(results.get(0).map_or(None, |t| t.to_owned().into_boolean()).expect("boolean"), true);
         assert_eq!(results.get(1).map_or(None, |t| t.to_owned().into_long()).expect("long"), 25);
     }
+
+    #[test]
+    fn test_shared_store() {
+        let mut store = Store::open("").expect("store connection");
+        store.transact(r#"[
+            [:db/add "s" :db/ident :foo/boolean]
+            [:db/add "s" :db/valueType :db.type/boolean]
+            [:db/add "s" :db/cardinality :db.cardinality/one]
+        ]"#).expect("successful transaction");
+
+        store.transact(r#"[
+            [:db/add "u" :foo/boolean true]
+            [:db/add "p" :foo/boolean false]
+        ]"#).expect("successful transaction");
+
+        // Read-only queries only need a shared reference, so builders can coexist.
+        let store = &store;
+        let mut yes = QueryBuilder::new(store, r#"[:find [?x ...] :in ?v :where [?x :foo/boolean ?v]]"#);
+        let mut no = QueryBuilder::new(store, r#"[:find [?x ...] :in ?v :where [?x :foo/boolean ?v]]"#);
+        yes.bind_value("?v", true);
+        no.bind_value("?v", false);
+        assert_eq!(yes.execute_coll().expect("CollResult").len(), 1);
+        assert_eq!(no.execute_coll().expect("CollResult").len(), 1);
+    }
 }